@@ -1,19 +1,21 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.1
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "inventory")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
-    #[sea_orm(unique)]
-    pub name: String,
-    pub quantity: i32,
-    pub capacity: i32,
+    #[sea_orm(column_type = "Decimal(Some((16, 4)))")]
+    pub quantity: Decimal,
+    #[sea_orm(column_type = "Decimal(Some((16, 4)))")]
+    pub capacity: Decimal,
     #[sea_orm(column_type = "Double")]
     pub stock: f64,
     pub product_id: i32,
+    pub tenant_id: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -26,6 +28,14 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     Product,
+    #[sea_orm(
+        belongs_to = "super::tenant::Entity",
+        from = "Column::TenantId",
+        to = "super::tenant::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Tenant,
 }
 
 impl Related<super::product::Entity> for Entity {
@@ -34,4 +44,10 @@ impl Related<super::product::Entity> for Entity {
     }
 }
 
+impl Related<super::tenant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Tenant.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -2,5 +2,31 @@
 
 pub mod prelude;
 
+pub mod api_key;
+pub mod app_user;
+pub mod attribute_definition;
+pub mod audit_log;
+pub mod bom_component;
+pub mod category;
+pub mod count_entry;
+pub mod count_line;
+pub mod count_session;
+pub mod idempotency_key;
 pub mod inventory;
+pub mod lot;
+pub mod low_stock_alert;
+pub mod outbox_event;
 pub mod product;
+pub mod product_attribute;
+pub mod rma;
+pub mod rma_item;
+pub mod sale;
+pub mod serial_event;
+pub mod serial_unit;
+pub mod stock_adjustment;
+pub mod tenant;
+pub mod unit_conversion;
+pub mod webhook_attempt;
+pub mod webhook_delivery;
+pub mod webhook_subscription;
+pub mod write_off;
@@ -1,4 +1,30 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.1
 
+pub use super::api_key::Entity as ApiKey;
+pub use super::app_user::Entity as AppUser;
+pub use super::attribute_definition::Entity as AttributeDefinition;
+pub use super::audit_log::Entity as AuditLog;
+pub use super::bom_component::Entity as BomComponent;
+pub use super::category::Entity as Category;
+pub use super::count_entry::Entity as CountEntry;
+pub use super::count_line::Entity as CountLine;
+pub use super::count_session::Entity as CountSession;
+pub use super::idempotency_key::Entity as IdempotencyKey;
 pub use super::inventory::Entity as Inventory;
+pub use super::lot::Entity as Lot;
+pub use super::low_stock_alert::Entity as LowStockAlert;
+pub use super::outbox_event::Entity as OutboxEvent;
 pub use super::product::Entity as Product;
+pub use super::product_attribute::Entity as ProductAttribute;
+pub use super::rma::Entity as Rma;
+pub use super::rma_item::Entity as RmaItem;
+pub use super::sale::Entity as Sale;
+pub use super::serial_event::Entity as SerialEvent;
+pub use super::serial_unit::Entity as SerialUnit;
+pub use super::stock_adjustment::Entity as StockAdjustment;
+pub use super::tenant::Entity as Tenant;
+pub use super::unit_conversion::Entity as UnitConversion;
+pub use super::webhook_attempt::Entity as WebhookAttempt;
+pub use super::webhook_delivery::Entity as WebhookDelivery;
+pub use super::webhook_subscription::Entity as WebhookSubscription;
+pub use super::write_off::Entity as WriteOff;
@@ -1,22 +1,93 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.1
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "product")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
-    #[sea_orm(unique)]
     pub name: String,
     #[sea_orm(column_type = "Double")]
     pub price: f64,
+    pub serialized: bool,
+    pub base_unit: String,
+    pub quantity_precision: i32,
+    pub sku: String,
+    pub barcode: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub description: Option<String>,
+    pub brand: Option<String>,
+    pub category_id: Option<i32>,
+    pub parent_id: Option<i32>,
+    pub size: Option<String>,
+    pub colour: Option<String>,
+    pub archived_at: Option<DateTimeWithTimeZone>,
+    pub tenant_id: i32,
+    pub price_override: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::category::Entity",
+        from = "Column::CategoryId",
+        to = "super::category::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Category,
+    #[sea_orm(
+        belongs_to = "Entity",
+        from = "Column::ParentId",
+        to = "Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    SelfRef,
+    #[sea_orm(has_many = "super::count_line::Entity")]
+    CountLine,
     #[sea_orm(has_many = "super::inventory::Entity")]
     Inventory,
+    #[sea_orm(has_many = "super::lot::Entity")]
+    Lot,
+    #[sea_orm(has_many = "super::low_stock_alert::Entity")]
+    LowStockAlert,
+    #[sea_orm(has_many = "super::product_attribute::Entity")]
+    ProductAttribute,
+    #[sea_orm(has_many = "super::rma_item::Entity")]
+    RmaItem,
+    #[sea_orm(has_many = "super::sale::Entity")]
+    Sale,
+    #[sea_orm(has_many = "super::serial_unit::Entity")]
+    SerialUnit,
+    #[sea_orm(has_many = "super::stock_adjustment::Entity")]
+    StockAdjustment,
+    #[sea_orm(has_many = "super::unit_conversion::Entity")]
+    UnitConversion,
+    #[sea_orm(has_many = "super::write_off::Entity")]
+    WriteOff,
+    #[sea_orm(
+        belongs_to = "super::tenant::Entity",
+        from = "Column::TenantId",
+        to = "super::tenant::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Tenant,
+}
+
+impl Related<super::category::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Category.def()
+    }
+}
+
+impl Related<super::count_line::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CountLine.def()
+    }
 }
 
 impl Related<super::inventory::Entity> for Entity {
@@ -25,4 +96,64 @@ impl Related<super::inventory::Entity> for Entity {
     }
 }
 
+impl Related<super::lot::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Lot.def()
+    }
+}
+
+impl Related<super::low_stock_alert::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LowStockAlert.def()
+    }
+}
+
+impl Related<super::product_attribute::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProductAttribute.def()
+    }
+}
+
+impl Related<super::rma_item::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RmaItem.def()
+    }
+}
+
+impl Related<super::sale::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Sale.def()
+    }
+}
+
+impl Related<super::serial_unit::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SerialUnit.def()
+    }
+}
+
+impl Related<super::stock_adjustment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StockAdjustment.def()
+    }
+}
+
+impl Related<super::unit_conversion::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UnitConversion.def()
+    }
+}
+
+impl Related<super::write_off::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WriteOff.def()
+    }
+}
+
+impl Related<super::tenant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Tenant.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000001_create_product_table::Product;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000003_create_sale_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Sale::Table)
+                    .col(
+                        ColumnDef::new(Sale::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Sale::ProductId).integer().not_null())
+                    .col(ColumnDef::new(Sale::Quantity).integer().not_null())
+                    .col(ColumnDef::new(Sale::UnitPrice).double().not_null())
+                    .col(ColumnDef::new(Sale::SoldAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-sale-product_id")
+                            .from(Sale::Table, Sale::ProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Sale::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Sale{
+    Table,
+    Id,
+    ProductId,
+    Quantity,
+    UnitPrice,
+    SoldAt,
+}
@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000003_create_sale_table::Sale;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000004_create_rma_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Rma::Table)
+                    .col(
+                        ColumnDef::new(Rma::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Rma::SaleId).integer().not_null())
+                    .col(ColumnDef::new(Rma::Quantity).integer().not_null())
+                    .col(ColumnDef::new(Rma::Reason).string().not_null())
+                    .col(ColumnDef::new(Rma::Status).string().not_null())
+                    .col(ColumnDef::new(Rma::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-rma-sale_id")
+                            .from(Rma::Table, Rma::SaleId)
+                            .to(Sale::Table, Sale::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Rma::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Rma{
+    Table,
+    Id,
+    SaleId,
+    Quantity,
+    Reason,
+    Status,
+    CreatedAt,
+}
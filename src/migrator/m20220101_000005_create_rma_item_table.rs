@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000001_create_product_table::Product;
+use super::m20220101_000004_create_rma_table::Rma;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000005_create_rma_item_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RmaItem::Table)
+                    .col(
+                        ColumnDef::new(RmaItem::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(RmaItem::RmaId).integer().not_null())
+                    .col(ColumnDef::new(RmaItem::ProductId).integer().not_null())
+                    .col(ColumnDef::new(RmaItem::Quantity).integer().not_null())
+                    .col(ColumnDef::new(RmaItem::Disposition).string().not_null())
+                    .col(ColumnDef::new(RmaItem::InspectedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-rma_item-rma_id")
+                            .from(RmaItem::Table, RmaItem::RmaId)
+                            .to(Rma::Table, Rma::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-rma_item-product_id")
+                            .from(RmaItem::Table, RmaItem::ProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RmaItem::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum RmaItem{
+    Table,
+    Id,
+    RmaId,
+    ProductId,
+    Quantity,
+    Disposition,
+    InspectedAt,
+}
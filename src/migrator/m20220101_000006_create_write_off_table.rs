@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000001_create_product_table::Product;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000006_create_write_off_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WriteOff::Table)
+                    .col(
+                        ColumnDef::new(WriteOff::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(WriteOff::ProductId).integer().not_null())
+                    .col(ColumnDef::new(WriteOff::Quantity).integer().not_null())
+                    .col(ColumnDef::new(WriteOff::Value).double().not_null())
+                    .col(ColumnDef::new(WriteOff::Reason).string().not_null())
+                    .col(ColumnDef::new(WriteOff::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-write_off-product_id")
+                            .from(WriteOff::Table, WriteOff::ProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WriteOff::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum WriteOff{
+    Table,
+    Id,
+    ProductId,
+    Quantity,
+    Value,
+    Reason,
+    CreatedAt,
+}
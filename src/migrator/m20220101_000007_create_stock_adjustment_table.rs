@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000001_create_product_table::Product;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000007_create_stock_adjustment_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(StockAdjustment::Table)
+                    .col(
+                        ColumnDef::new(StockAdjustment::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(StockAdjustment::ProductId).integer().not_null())
+                    .col(ColumnDef::new(StockAdjustment::PreviousQuantity).integer().not_null())
+                    .col(ColumnDef::new(StockAdjustment::NewQuantity).integer().not_null())
+                    .col(ColumnDef::new(StockAdjustment::Reason).string().not_null())
+                    .col(ColumnDef::new(StockAdjustment::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-stock_adjustment-product_id")
+                            .from(StockAdjustment::Table, StockAdjustment::ProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(StockAdjustment::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum StockAdjustment{
+    Table,
+    Id,
+    ProductId,
+    PreviousQuantity,
+    NewQuantity,
+    Reason,
+    CreatedAt,
+}
@@ -0,0 +1,47 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000008_create_count_session_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CountSession::Table)
+                    .col(
+                        ColumnDef::new(CountSession::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CountSession::Status).string().not_null())
+                    .col(ColumnDef::new(CountSession::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(CountSession::ApprovedAt).timestamp_with_time_zone())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CountSession::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum CountSession{
+    Table,
+    Id,
+    Status,
+    CreatedAt,
+    ApprovedAt,
+}
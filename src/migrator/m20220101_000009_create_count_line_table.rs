@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000001_create_product_table::Product;
+use super::m20220101_000008_create_count_session_table::CountSession;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000009_create_count_line_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CountLine::Table)
+                    .col(
+                        ColumnDef::new(CountLine::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CountLine::SessionId).integer().not_null())
+                    .col(ColumnDef::new(CountLine::ProductId).integer().not_null())
+                    .col(ColumnDef::new(CountLine::ExpectedQuantity).integer().not_null())
+                    .col(ColumnDef::new(CountLine::UnitPrice).double().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-count_line-session_id")
+                            .from(CountLine::Table, CountLine::SessionId)
+                            .to(CountSession::Table, CountSession::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-count_line-product_id")
+                            .from(CountLine::Table, CountLine::ProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CountLine::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum CountLine{
+    Table,
+    Id,
+    SessionId,
+    ProductId,
+    ExpectedQuantity,
+    UnitPrice,
+}
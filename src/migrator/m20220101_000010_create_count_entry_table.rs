@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000009_create_count_line_table::CountLine;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000010_create_count_entry_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CountEntry::Table)
+                    .col(
+                        ColumnDef::new(CountEntry::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CountEntry::LineId).integer().not_null())
+                    .col(ColumnDef::new(CountEntry::Counter).string().not_null())
+                    .col(ColumnDef::new(CountEntry::CountedQuantity).integer().not_null())
+                    .col(ColumnDef::new(CountEntry::CountedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-count_entry-line_id")
+                            .from(CountEntry::Table, CountEntry::LineId)
+                            .to(CountLine::Table, CountLine::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CountEntry::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum CountEntry{
+    Table,
+    Id,
+    LineId,
+    Counter,
+    CountedQuantity,
+    CountedAt,
+}
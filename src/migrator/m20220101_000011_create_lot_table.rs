@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000001_create_product_table::Product;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000011_create_lot_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Lot::Table)
+                    .col(
+                        ColumnDef::new(Lot::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Lot::ProductId).integer().not_null())
+                    .col(ColumnDef::new(Lot::Number).string().not_null())
+                    .col(ColumnDef::new(Lot::ManufacturedOn).date().not_null())
+                    .col(ColumnDef::new(Lot::ExpiresOn).date().not_null())
+                    .col(ColumnDef::new(Lot::Quantity).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-lot-product_id")
+                            .from(Lot::Table, Lot::ProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Lot::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Lot{
+    Table,
+    Id,
+    ProductId,
+    Number,
+    ManufacturedOn,
+    ExpiresOn,
+    Quantity,
+}
@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000001_create_product_table::Product;
+use super::m20220101_000003_create_sale_table::Sale;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000013_create_serial_unit_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SerialUnit::Table)
+                    .col(
+                        ColumnDef::new(SerialUnit::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SerialUnit::ProductId).integer().not_null())
+                    .col(ColumnDef::new(SerialUnit::SerialNumber).string().not_null().unique_key())
+                    .col(ColumnDef::new(SerialUnit::Status).string().not_null())
+                    .col(ColumnDef::new(SerialUnit::SaleId).integer())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-serial_unit-product_id")
+                            .from(SerialUnit::Table, SerialUnit::ProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-serial_unit-sale_id")
+                            .from(SerialUnit::Table, SerialUnit::SaleId)
+                            .to(Sale::Table, Sale::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SerialUnit::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum SerialUnit{
+    Table,
+    Id,
+    ProductId,
+    SerialNumber,
+    Status,
+    SaleId,
+}
@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000013_create_serial_unit_table::SerialUnit;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000014_create_serial_event_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SerialEvent::Table)
+                    .col(
+                        ColumnDef::new(SerialEvent::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SerialEvent::SerialUnitId).integer().not_null())
+                    .col(ColumnDef::new(SerialEvent::Status).string().not_null())
+                    .col(ColumnDef::new(SerialEvent::Reference).string().not_null())
+                    .col(ColumnDef::new(SerialEvent::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-serial_event-serial_unit_id")
+                            .from(SerialEvent::Table, SerialEvent::SerialUnitId)
+                            .to(SerialUnit::Table, SerialUnit::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SerialEvent::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum SerialEvent{
+    Table,
+    Id,
+    SerialUnitId,
+    Status,
+    Reference,
+    CreatedAt,
+}
@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000001_create_product_table::Product;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000015_create_bom_component_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BomComponent::Table)
+                    .col(
+                        ColumnDef::new(BomComponent::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(BomComponent::ParentProductId).integer().not_null())
+                    .col(ColumnDef::new(BomComponent::ComponentProductId).integer().not_null())
+                    .col(ColumnDef::new(BomComponent::Quantity).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bom_component-parent_product_id")
+                            .from(BomComponent::Table, BomComponent::ParentProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bom_component-component_product_id")
+                            .from(BomComponent::Table, BomComponent::ComponentProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BomComponent::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum BomComponent{
+    Table,
+    Id,
+    ParentProductId,
+    ComponentProductId,
+    Quantity,
+}
@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000001_create_product_table::Product;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000017_create_unit_conversion_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(UnitConversion::Table)
+                    .col(
+                        ColumnDef::new(UnitConversion::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(UnitConversion::ProductId).integer().not_null())
+                    .col(ColumnDef::new(UnitConversion::Unit).string().not_null())
+                    .col(ColumnDef::new(UnitConversion::Factor).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-unit_conversion-product_id")
+                            .from(UnitConversion::Table, UnitConversion::ProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UnitConversion::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum UnitConversion{
+    Table,
+    Id,
+    ProductId,
+    Unit,
+    Factor,
+}
@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000019_alter_inventory_quantity_to_decimal" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can't change a column's type, but its integer columns already keep fractional values
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Inventory::Table)
+                    .modify_column(ColumnDef::new(Inventory::Quantity).decimal_len(16, 4).not_null())
+                    .modify_column(ColumnDef::new(Inventory::Capacity).decimal_len(16, 4).not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Inventory::Table)
+                    .modify_column(ColumnDef::new(Inventory::Quantity).integer().not_null())
+                    .modify_column(ColumnDef::new(Inventory::Capacity).integer().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Inventory{
+    Table,
+    Quantity,
+    Capacity,
+}
@@ -0,0 +1,88 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+use super::m20220101_000021_create_category_table::Category;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000022_add_catalogue_fields_to_product" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SKU starts out nullable so existing rows can be backfilled before it becomes required.
+        // SQLite adds one column per statement, and can't add a unique column or a foreign key to an
+        // existing table; its SKUs and barcodes become unique with the per-tenant indexes added later
+        let sqlite = manager.get_database_backend() == DbBackend::Sqlite;
+        let mut sku = ColumnDef::new(Product::Sku);
+        let mut barcode = ColumnDef::new(Product::Barcode);
+        if !sqlite {
+            sku.unique_key();
+            barcode.unique_key();
+        }
+        let columns = [
+            sku.string().to_owned(),
+            barcode.string().to_owned(),
+            ColumnDef::new(Product::Description).text().to_owned(),
+            ColumnDef::new(Product::Brand).string().to_owned(),
+            ColumnDef::new(Product::CategoryId).integer().to_owned(),
+        ];
+        for mut column in columns {
+            manager
+                .alter_table(Table::alter().table(Product::Table).add_column(&mut column).to_owned())
+                .await?;
+        }
+        if sqlite {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Product::Table)
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk-product-category_id")
+                            .from_tbl(Product::Table)
+                            .from_col(Product::CategoryId)
+                            .to_tbl(Category::Table)
+                            .to_col(Category::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DbBackend::Sqlite {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Product::Table)
+                        .drop_foreign_key(Alias::new("fk-product-category_id"))
+                        .to_owned(),
+                )
+                .await?;
+        }
+        for column in [Product::CategoryId, Product::Brand, Product::Description, Product::Barcode, Product::Sku] {
+            manager
+                .alter_table(Table::alter().table(Product::Table).drop_column(column).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Product{
+    Table,
+    Sku,
+    Barcode,
+    Description,
+    Brand,
+    CategoryId,
+}
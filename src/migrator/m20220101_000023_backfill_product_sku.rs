@@ -0,0 +1,64 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000023_backfill_product_sku" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Give every existing product a SKU derived from its ID, e.g. SKU-000042, then require one.
+        // SQLite has no LPAD, so it pads by taking the last six characters of the zero-prefixed ID
+        let sku = match manager.get_database_backend() {
+            DbBackend::Postgres => "'SKU-' || LPAD(CAST(id AS TEXT), GREATEST(6, LENGTH(CAST(id AS TEXT))), '0')",
+            DbBackend::MySql => "CONCAT('SKU-', LPAD(id, GREATEST(6, LENGTH(id)), '0'))",
+            DbBackend::Sqlite => "'SKU-' || CASE WHEN LENGTH(id) >= 6 THEN CAST(id AS TEXT) ELSE SUBSTR('000000' || id, -6) END",
+        };
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Product::Table)
+                    .value(Product::Sku, Expr::cust(sku))
+                    .and_where(Expr::col(Product::Sku).is_null())
+                    .to_owned(),
+            )
+            .await?;
+        // SQLite can't change a column's constraints in place, so it relies on the app always setting a SKU
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Product::Table)
+                    .modify_column(ColumnDef::new(Product::Sku).string().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Product::Table)
+                    .modify_column(ColumnDef::new(Product::Sku).string().null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Product{
+    Table,
+    Sku,
+}
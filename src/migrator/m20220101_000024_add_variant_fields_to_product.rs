@@ -0,0 +1,74 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000024_add_variant_fields_to_product" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite adds one column per statement and can't add a foreign key to an existing table
+        let columns = [
+            ColumnDef::new(Product::ParentId).integer().to_owned(),
+            ColumnDef::new(Product::Size).string().to_owned(),
+            ColumnDef::new(Product::Colour).string().to_owned(),
+        ];
+        for mut column in columns {
+            manager
+                .alter_table(Table::alter().table(Product::Table).add_column(&mut column).to_owned())
+                .await?;
+        }
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Product::Table)
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk-product-parent_id")
+                            .from_tbl(Product::Table)
+                            .from_col(Product::ParentId)
+                            .to_tbl(Product::Table)
+                            .to_col(Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DbBackend::Sqlite {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Product::Table)
+                        .drop_foreign_key(Alias::new("fk-product-parent_id"))
+                        .to_owned(),
+                )
+                .await?;
+        }
+        for column in [Product::Colour, Product::Size, Product::ParentId] {
+            manager
+                .alter_table(Table::alter().table(Product::Table).drop_column(column).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Product{
+    Table,
+    Id,
+    ParentId,
+    Size,
+    Colour,
+}
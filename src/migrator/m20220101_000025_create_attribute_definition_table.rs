@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000021_create_category_table::Category;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000025_create_attribute_definition_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Definitions without a category apply to every product
+        manager
+            .create_table(
+                Table::create()
+                    .table(AttributeDefinition::Table)
+                    .col(
+                        ColumnDef::new(AttributeDefinition::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AttributeDefinition::CategoryId).integer())
+                    .col(ColumnDef::new(AttributeDefinition::Name).string().not_null())
+                    .col(ColumnDef::new(AttributeDefinition::DataType).string().not_null())
+                    .col(ColumnDef::new(AttributeDefinition::AllowedValues).text())
+                    .col(ColumnDef::new(AttributeDefinition::Required).boolean().not_null().default(false))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-attribute_definition-category_id")
+                            .from(AttributeDefinition::Table, AttributeDefinition::CategoryId)
+                            .to(Category::Table, Category::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AttributeDefinition::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum AttributeDefinition{
+    Table,
+    Id,
+    CategoryId,
+    Name,
+    DataType,
+    AllowedValues,
+    Required,
+}
@@ -0,0 +1,78 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000001_create_product_table::Product;
+use super::m20220101_000025_create_attribute_definition_table::AttributeDefinition;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000026_create_product_attribute_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Each value is kept in the column matching its definition's type so it can be filtered on
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProductAttribute::Table)
+                    .col(
+                        ColumnDef::new(ProductAttribute::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ProductAttribute::ProductId).integer().not_null())
+                    .col(ColumnDef::new(ProductAttribute::DefinitionId).integer().not_null())
+                    .col(ColumnDef::new(ProductAttribute::TextValue).text())
+                    .col(ColumnDef::new(ProductAttribute::NumberValue).double())
+                    .col(ColumnDef::new(ProductAttribute::BooleanValue).boolean())
+                    .col(ColumnDef::new(ProductAttribute::DateValue).date())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-product_attribute-product_id")
+                            .from(ProductAttribute::Table, ProductAttribute::ProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-product_attribute-definition_id")
+                            .from(ProductAttribute::Table, ProductAttribute::DefinitionId)
+                            .to(AttributeDefinition::Table, AttributeDefinition::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx-product_attribute-product_id-definition_id")
+                            .col(ProductAttribute::ProductId)
+                            .col(ProductAttribute::DefinitionId)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ProductAttribute::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum ProductAttribute{
+    Table,
+    Id,
+    ProductId,
+    DefinitionId,
+    TextValue,
+    NumberValue,
+    BooleanValue,
+    DateValue,
+}
@@ -0,0 +1,81 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000027_add_product_search_indexes" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        match manager.get_database_backend() {
+            // Postgres keeps a weighted tsvector of name, brand and description, and a trigram index on name for fuzzy matching
+            DbBackend::Postgres => {
+                db.execute_unprepared("CREATE EXTENSION IF NOT EXISTS pg_trgm").await?;
+                db.execute_unprepared(
+                    "ALTER TABLE product ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+                        setweight(to_tsvector('english', coalesce(name, '')), 'A') ||
+                        setweight(to_tsvector('english', coalesce(brand, '')), 'B') ||
+                        setweight(to_tsvector('english', coalesce(description, '')), 'C')
+                    ) STORED",
+                )
+                .await?;
+                db.execute_unprepared("CREATE INDEX \"idx-product-search_vector\" ON product USING GIN (search_vector)").await?;
+                db.execute_unprepared("CREATE INDEX \"idx-product-name_trgm\" ON product USING GIN (name gin_trgm_ops)").await?;
+            }
+            // SQLite mirrors the same fields into an FTS5 table, kept in step by triggers
+            DbBackend::Sqlite => {
+                db.execute_unprepared(
+                    "CREATE VIRTUAL TABLE product_fts USING fts5(name, brand, description, content='product', content_rowid='id')",
+                )
+                .await?;
+                db.execute_unprepared(
+                    "CREATE TRIGGER product_fts_insert AFTER INSERT ON product BEGIN
+                        INSERT INTO product_fts(rowid, name, brand, description) VALUES (new.id, new.name, new.brand, new.description);
+                    END",
+                )
+                .await?;
+                db.execute_unprepared(
+                    "CREATE TRIGGER product_fts_delete AFTER DELETE ON product BEGIN
+                        INSERT INTO product_fts(product_fts, rowid, name, brand, description) VALUES ('delete', old.id, old.name, old.brand, old.description);
+                    END",
+                )
+                .await?;
+                db.execute_unprepared(
+                    "CREATE TRIGGER product_fts_update AFTER UPDATE ON product BEGIN
+                        INSERT INTO product_fts(product_fts, rowid, name, brand, description) VALUES ('delete', old.id, old.name, old.brand, old.description);
+                        INSERT INTO product_fts(rowid, name, brand, description) VALUES (new.id, new.name, new.brand, new.description);
+                    END",
+                )
+                .await?;
+                db.execute_unprepared("INSERT INTO product_fts(product_fts) VALUES ('rebuild')").await?;
+            }
+            DbBackend::MySql => {}
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        match manager.get_database_backend() {
+            DbBackend::Postgres => {
+                db.execute_unprepared("DROP INDEX IF EXISTS \"idx-product-name_trgm\"").await?;
+                db.execute_unprepared("DROP INDEX IF EXISTS \"idx-product-search_vector\"").await?;
+                db.execute_unprepared("ALTER TABLE product DROP COLUMN IF EXISTS search_vector").await?;
+            }
+            DbBackend::Sqlite => {
+                db.execute_unprepared("DROP TRIGGER IF EXISTS product_fts_update").await?;
+                db.execute_unprepared("DROP TRIGGER IF EXISTS product_fts_delete").await?;
+                db.execute_unprepared("DROP TRIGGER IF EXISTS product_fts_insert").await?;
+                db.execute_unprepared("DROP TABLE IF EXISTS product_fts").await?;
+            }
+            DbBackend::MySql => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000030_create_app_user_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AppUser::Table)
+                    .col(
+                        ColumnDef::new(AppUser::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AppUser::Username).string().not_null().unique_key())
+                    .col(ColumnDef::new(AppUser::Role).string().not_null())
+                    .col(ColumnDef::new(AppUser::Active).boolean().not_null().default(true))
+                    .col(ColumnDef::new(AppUser::CreatedAt).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AppUser::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum AppUser{
+    Table,
+    Id,
+    Username,
+    Role,
+    Active,
+    CreatedAt,
+}
@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000030_create_app_user_table::AppUser;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000031_create_api_key_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Only a hash of each key is stored; the prefix identifies a key without revealing it
+        manager
+            .create_table(
+                Table::create()
+                    .table(ApiKey::Table)
+                    .col(
+                        ColumnDef::new(ApiKey::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ApiKey::UserId).integer().not_null())
+                    .col(ColumnDef::new(ApiKey::Label).string().not_null())
+                    .col(ColumnDef::new(ApiKey::Prefix).string().not_null())
+                    .col(ColumnDef::new(ApiKey::KeyHash).string().not_null().unique_key())
+                    .col(ColumnDef::new(ApiKey::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(ApiKey::RevokedAt).timestamp_with_time_zone())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-api_key-user_id")
+                            .from(ApiKey::Table, ApiKey::UserId)
+                            .to(AppUser::Table, AppUser::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiKey::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum ApiKey{
+    Table,
+    Id,
+    UserId,
+    Label,
+    Prefix,
+    KeyHash,
+    CreatedAt,
+    RevokedAt,
+}
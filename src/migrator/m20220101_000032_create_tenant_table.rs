@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000032_create_tenant_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Tenant::Table)
+                    .col(
+                        ColumnDef::new(Tenant::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Tenant::Name).string().not_null().unique_key())
+                    .col(ColumnDef::new(Tenant::CreatedAt).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        // Existing rows are moved into a default tenant by the next migration
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(Tenant::Table)
+                    .columns([Tenant::Name, Tenant::CreatedAt])
+                    .values_panic(["Default".into(), Expr::current_timestamp().into()])
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Tenant::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Tenant{
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+}
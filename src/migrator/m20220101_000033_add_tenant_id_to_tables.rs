@@ -0,0 +1,160 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+use super::m20220101_000032_create_tenant_table::Tenant;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000033_add_tenant_id_to_tables" 
+    }
+}
+
+// Tables whose rows belong to a tenant, and the name of each one's tenant foreign key
+const TENANT_TABLES: [(&str, &str); 6] = [
+    ("product", "fk-product-tenant_id"),
+    ("inventory", "fk-inventory-tenant_id"),
+    ("app_user", "fk-app_user-tenant_id"),
+    ("audit_log", "fk-audit_log-tenant_id"),
+    ("category", "fk-category-tenant_id"),
+    ("attribute_definition", "fk-attribute_definition-tenant_id"),
+];
+// Fields that are unique within a tenant rather than across the deployment
+const PER_TENANT_UNIQUE: [(&str, &str, &[&str]); 5] = [
+    ("product", "idx-product-tenant_id-name", &["name"]),
+    ("product", "idx-product-tenant_id-sku", &["sku"]),
+    ("product", "idx-product-tenant_id-barcode", &["barcode"]),
+    ("category", "idx-category-tenant_id-name", &["name"]),
+    ("attribute_definition", "idx-attribute_definition-tenant_id-category_id-name", &["category_id", "name"]),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Tenant ID starts out nullable so existing rows can be given the default tenant before it becomes required.
+        // SQLite can't add a foreign key to an existing table or make a column required in place
+        let sqlite = manager.get_database_backend() == DbBackend::Sqlite;
+        for (table, foreign_key) in TENANT_TABLES {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Alias::new(table))
+                        .add_column(ColumnDef::new(TenantId::TenantId).integer())
+                        .to_owned(),
+                )
+                .await?;
+            if !sqlite {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Alias::new(table))
+                            .add_foreign_key(
+                                TableForeignKey::new()
+                                    .name(foreign_key)
+                                    .from_tbl(Alias::new(table))
+                                    .from_col(TenantId::TenantId)
+                                    .to_tbl(Tenant::Table)
+                                    .to_col(Tenant::Id)
+                                    .on_delete(ForeignKeyAction::Cascade),
+                            )
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+            manager
+                .exec_stmt(
+                    Query::update()
+                        .table(Alias::new(table))
+                        .value(TenantId::TenantId, Expr::cust("(SELECT MIN(id) FROM tenant)"))
+                        .and_where(Expr::col(TenantId::TenantId).is_null())
+                        .to_owned(),
+                )
+                .await?;
+            if sqlite {
+                continue;
+            }
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Alias::new(table))
+                        .modify_column(ColumnDef::new(TenantId::TenantId).integer().not_null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // SKU, barcode and category name were unique across the deployment; SQLite can't drop a column constraint in place
+        let db = manager.get_connection();
+        match manager.get_database_backend() {
+            DbBackend::Postgres => {
+                db.execute_unprepared("ALTER TABLE product DROP CONSTRAINT IF EXISTS product_sku_key").await?;
+                db.execute_unprepared("ALTER TABLE product DROP CONSTRAINT IF EXISTS product_barcode_key").await?;
+                db.execute_unprepared("ALTER TABLE category DROP CONSTRAINT IF EXISTS category_name_key").await?;
+            }
+            DbBackend::MySql => {
+                db.execute_unprepared("ALTER TABLE product DROP INDEX sku").await?;
+                db.execute_unprepared("ALTER TABLE product DROP INDEX barcode").await?;
+                db.execute_unprepared("ALTER TABLE category DROP INDEX name").await?;
+            }
+            DbBackend::Sqlite => {}
+        }
+        for (table, index, columns) in PER_TENANT_UNIQUE {
+            let mut per_tenant_index = Index::create();
+            per_tenant_index.name(index).table(Alias::new(table)).col(TenantId::TenantId).unique();
+            for column in columns {
+                per_tenant_index.col(Alias::new(*column));
+            }
+            manager.create_index(per_tenant_index.to_owned()).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (table, index, _) in PER_TENANT_UNIQUE {
+            manager
+                .drop_index(Index::drop().name(index).table(Alias::new(table)).to_owned())
+                .await?;
+        }
+        let db = manager.get_connection();
+        match manager.get_database_backend() {
+            DbBackend::Postgres => {
+                db.execute_unprepared("ALTER TABLE product ADD CONSTRAINT product_sku_key UNIQUE (sku)").await?;
+                db.execute_unprepared("ALTER TABLE product ADD CONSTRAINT product_barcode_key UNIQUE (barcode)").await?;
+                db.execute_unprepared("ALTER TABLE category ADD CONSTRAINT category_name_key UNIQUE (name)").await?;
+            }
+            DbBackend::MySql => {
+                db.execute_unprepared("ALTER TABLE product ADD UNIQUE INDEX sku (sku)").await?;
+                db.execute_unprepared("ALTER TABLE product ADD UNIQUE INDEX barcode (barcode)").await?;
+                db.execute_unprepared("ALTER TABLE category ADD UNIQUE INDEX name (name)").await?;
+            }
+            DbBackend::Sqlite => {}
+        }
+        for (table, foreign_key) in TENANT_TABLES {
+            if manager.get_database_backend() != DbBackend::Sqlite {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Alias::new(table))
+                            .drop_foreign_key(Alias::new(foreign_key))
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Alias::new(table))
+                        .drop_column(TenantId::TenantId)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum TenantId{
+    TenantId,
+}
@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000034_add_integrity_constraints"
+    }
+}
+
+// Table, name and condition of each CHECK constraint, named so violations can be told apart
+const CHECK_CONSTRAINTS: [(&str, &str, &str); 3] = [
+    ("inventory", "chk_inventory_quantity_non_negative", "quantity >= 0"),
+    ("inventory", "chk_inventory_quantity_within_capacity", "quantity <= capacity"),
+    ("product", "chk_product_price_non_negative", "price >= 0"),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Each product has exactly one inventory row
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-inventory-product_id")
+                    .table(Inventory::Table)
+                    .col(Inventory::ProductId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // SQLite can't add a CHECK constraint to an existing table, so it relies on the app's own checks
+        let db = manager.get_connection();
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        for (table, constraint, condition) in CHECK_CONSTRAINTS {
+            db.execute_unprepared(&format!("ALTER TABLE {} ADD CONSTRAINT {} CHECK ({})", table, constraint, condition)).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        match manager.get_database_backend() {
+            DbBackend::Postgres => {
+                for (table, constraint, _) in CHECK_CONSTRAINTS {
+                    db.execute_unprepared(&format!("ALTER TABLE {} DROP CONSTRAINT {}", table, constraint)).await?;
+                }
+            }
+            DbBackend::MySql => {
+                for (table, constraint, _) in CHECK_CONSTRAINTS {
+                    db.execute_unprepared(&format!("ALTER TABLE {} DROP CHECK {}", table, constraint)).await?;
+                }
+            }
+            DbBackend::Sqlite => {}
+        }
+        manager
+            .drop_index(Index::drop().name("idx-inventory-product_id").table(Inventory::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Inventory{
+    Table,
+    ProductId,
+}
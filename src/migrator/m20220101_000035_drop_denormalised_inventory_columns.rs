@@ -0,0 +1,85 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000035_drop_denormalised_inventory_columns"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Inventory is named by its product, so the copied name goes
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Inventory::Table)
+                    .drop_column(Inventory::Name)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Stock becomes a generated column, so it can't drift from quantity and capacity.
+        // SQLite can only add virtual generated columns, computed when read
+        let stock_column = match manager.get_database_backend() {
+            DbBackend::Postgres => "stock double precision GENERATED ALWAYS AS (CAST(quantity / capacity AS double precision)) STORED NOT NULL",
+            DbBackend::MySql => "stock double GENERATED ALWAYS AS (quantity / capacity) STORED NOT NULL",
+            DbBackend::Sqlite => "stock real GENERATED ALWAYS AS (CAST(quantity AS real) / capacity) VIRTUAL NOT NULL",
+        };
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Inventory::Table)
+                    .drop_column(Inventory::Stock)
+                    .to_owned(),
+            )
+            .await?;
+        let db = manager.get_connection();
+        db.execute_unprepared(&format!("ALTER TABLE inventory ADD COLUMN {}", stock_column)).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Inventory::Table)
+                    .drop_column(Inventory::Stock)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Inventory::Table)
+                    .add_column(ColumnDef::new(Inventory::Stock).double().not_null().default(0.0))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Inventory::Table)
+                    .add_column(ColumnDef::new(Inventory::Name).string().not_null().default(""))
+                    .to_owned(),
+            )
+            .await?;
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "UPDATE inventory SET stock = quantity / capacity, \
+             name = (SELECT product.name FROM product WHERE product.id = inventory.product_id)",
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Inventory{
+    Table,
+    Name,
+    Stock,
+}
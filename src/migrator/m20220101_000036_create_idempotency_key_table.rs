@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000032_create_tenant_table::Tenant;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000036_create_idempotency_key_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The outcome of a keyed request is kept so a retry can be answered without applying it again
+        manager
+            .create_table(
+                Table::create()
+                    .table(IdempotencyKey::Table)
+                    .col(
+                        ColumnDef::new(IdempotencyKey::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(IdempotencyKey::TenantId).integer().not_null())
+                    .col(ColumnDef::new(IdempotencyKey::Key).string().not_null())
+                    .col(ColumnDef::new(IdempotencyKey::Operation).string().not_null())
+                    .col(ColumnDef::new(IdempotencyKey::RequestHash).string().not_null())
+                    .col(ColumnDef::new(IdempotencyKey::Response).text().not_null())
+                    .col(ColumnDef::new(IdempotencyKey::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-idempotency_key-tenant_id")
+                            .from(IdempotencyKey::Table, IdempotencyKey::TenantId)
+                            .to(Tenant::Table, Tenant::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        // Keys are chosen by clients, so they only need to be unique within a tenant
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-idempotency_key-tenant_id-key")
+                    .table(IdempotencyKey::Table)
+                    .col(IdempotencyKey::TenantId)
+                    .col(IdempotencyKey::Key)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(IdempotencyKey::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum IdempotencyKey{
+    Table,
+    Id,
+    TenantId,
+    Key,
+    Operation,
+    RequestHash,
+    Response,
+    CreatedAt,
+}
@@ -0,0 +1,79 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000032_create_tenant_table::Tenant;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000037_create_outbox_event_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Domain events are written here in the same transaction as the change they describe,
+        // and marked dispatched once every sink has them
+        manager
+            .create_table(
+                Table::create()
+                    .table(OutboxEvent::Table)
+                    .col(
+                        ColumnDef::new(OutboxEvent::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(OutboxEvent::TenantId).integer().not_null())
+                    .col(ColumnDef::new(OutboxEvent::EventType).string().not_null())
+                    .col(ColumnDef::new(OutboxEvent::ProductId).integer().not_null())
+                    .col(ColumnDef::new(OutboxEvent::Payload).text().not_null())
+                    .col(ColumnDef::new(OutboxEvent::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(OutboxEvent::DispatchedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(OutboxEvent::Attempts).integer().not_null().default(0))
+                    .col(ColumnDef::new(OutboxEvent::LastError).text())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-outbox_event-tenant_id")
+                            .from(OutboxEvent::Table, OutboxEvent::TenantId)
+                            .to(Tenant::Table, Tenant::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        // The dispatcher polls for events not yet dispatched, oldest first
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-outbox_event-dispatched_at-id")
+                    .table(OutboxEvent::Table)
+                    .col(OutboxEvent::DispatchedAt)
+                    .col(OutboxEvent::Id)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OutboxEvent::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum OutboxEvent{
+    Table,
+    Id,
+    TenantId,
+    EventType,
+    ProductId,
+    Payload,
+    CreatedAt,
+    DispatchedAt,
+    Attempts,
+    LastError,
+}
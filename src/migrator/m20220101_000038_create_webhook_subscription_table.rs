@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000032_create_tenant_table::Tenant;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000038_create_webhook_subscription_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The secret signs every payload sent to the URL, so it's kept as is rather than hashed
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookSubscription::Table)
+                    .col(
+                        ColumnDef::new(WebhookSubscription::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(WebhookSubscription::TenantId).integer().not_null())
+                    .col(ColumnDef::new(WebhookSubscription::Url).string().not_null())
+                    .col(ColumnDef::new(WebhookSubscription::EventType).string().not_null())
+                    .col(ColumnDef::new(WebhookSubscription::Secret).string().not_null())
+                    .col(ColumnDef::new(WebhookSubscription::Active).boolean().not_null().default(true))
+                    .col(ColumnDef::new(WebhookSubscription::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-webhook_subscription-tenant_id")
+                            .from(WebhookSubscription::Table, WebhookSubscription::TenantId)
+                            .to(Tenant::Table, Tenant::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WebhookSubscription::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum WebhookSubscription{
+    Table,
+    Id,
+    TenantId,
+    Url,
+    EventType,
+    Secret,
+    Active,
+    CreatedAt,
+}
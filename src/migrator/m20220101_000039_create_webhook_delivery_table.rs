@@ -0,0 +1,101 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000032_create_tenant_table::Tenant;
+use super::m20220101_000038_create_webhook_subscription_table::WebhookSubscription;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000039_create_webhook_delivery_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per event per subscription, pending until delivered or dead-lettered
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookDelivery::Table)
+                    .col(
+                        ColumnDef::new(WebhookDelivery::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(WebhookDelivery::TenantId).integer().not_null())
+                    .col(ColumnDef::new(WebhookDelivery::SubscriptionId).integer().not_null())
+                    .col(ColumnDef::new(WebhookDelivery::OutboxEventId).integer().not_null())
+                    .col(ColumnDef::new(WebhookDelivery::EventType).string().not_null())
+                    .col(ColumnDef::new(WebhookDelivery::Payload).text().not_null())
+                    .col(ColumnDef::new(WebhookDelivery::Status).string().not_null())
+                    .col(ColumnDef::new(WebhookDelivery::Attempts).integer().not_null().default(0))
+                    .col(ColumnDef::new(WebhookDelivery::NextAttemptAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(WebhookDelivery::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(WebhookDelivery::DeliveredAt).timestamp_with_time_zone())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-webhook_delivery-tenant_id")
+                            .from(WebhookDelivery::Table, WebhookDelivery::TenantId)
+                            .to(Tenant::Table, Tenant::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-webhook_delivery-subscription_id")
+                            .from(WebhookDelivery::Table, WebhookDelivery::SubscriptionId)
+                            .to(WebhookSubscription::Table, WebhookSubscription::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        // An event is queued at most once per subscription, and the worker polls for due deliveries
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-webhook_delivery-subscription_id-outbox_event_id")
+                    .table(WebhookDelivery::Table)
+                    .col(WebhookDelivery::SubscriptionId)
+                    .col(WebhookDelivery::OutboxEventId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-webhook_delivery-status-next_attempt_at")
+                    .table(WebhookDelivery::Table)
+                    .col(WebhookDelivery::Status)
+                    .col(WebhookDelivery::NextAttemptAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WebhookDelivery::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum WebhookDelivery{
+    Table,
+    Id,
+    TenantId,
+    SubscriptionId,
+    OutboxEventId,
+    EventType,
+    Payload,
+    Status,
+    Attempts,
+    NextAttemptAt,
+    CreatedAt,
+    DeliveredAt,
+}
@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20220101_000039_create_webhook_delivery_table::WebhookDelivery;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000040_create_webhook_attempt_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Delivery log: every request made for a delivery, with the response status or the error
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookAttempt::Table)
+                    .col(
+                        ColumnDef::new(WebhookAttempt::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(WebhookAttempt::DeliveryId).integer().not_null())
+                    .col(ColumnDef::new(WebhookAttempt::StatusCode).integer())
+                    .col(ColumnDef::new(WebhookAttempt::Error).text())
+                    .col(ColumnDef::new(WebhookAttempt::AttemptedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-webhook_attempt-delivery_id")
+                            .from(WebhookAttempt::Table, WebhookAttempt::DeliveryId)
+                            .to(WebhookDelivery::Table, WebhookDelivery::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WebhookAttempt::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum WebhookAttempt{
+    Table,
+    Id,
+    DeliveryId,
+    StatusCode,
+    Error,
+    AttemptedAt,
+}
@@ -0,0 +1,98 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+use super::m20220101_000001_create_product_table::Product;
+use super::m20220101_000032_create_tenant_table::Tenant;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000041_create_low_stock_alert_table" 
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per time a product's stock fell to the low-stock threshold, open until it recovers
+        manager
+            .create_table(
+                Table::create()
+                    .table(LowStockAlert::Table)
+                    .col(
+                        ColumnDef::new(LowStockAlert::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(LowStockAlert::TenantId).integer().not_null())
+                    .col(ColumnDef::new(LowStockAlert::ProductId).integer().not_null())
+                    .col(ColumnDef::new(LowStockAlert::Quantity).decimal_len(16, 4).not_null())
+                    .col(ColumnDef::new(LowStockAlert::Capacity).decimal_len(16, 4).not_null())
+                    .col(ColumnDef::new(LowStockAlert::Threshold).double().not_null())
+                    .col(ColumnDef::new(LowStockAlert::RaisedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(LowStockAlert::NotifiedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(LowStockAlert::ResolvedAt).timestamp_with_time_zone())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-low_stock_alert-tenant_id")
+                            .from(LowStockAlert::Table, LowStockAlert::TenantId)
+                            .to(Tenant::Table, Tenant::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-low_stock_alert-product_id")
+                            .from(LowStockAlert::Table, LowStockAlert::ProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-low_stock_alert-tenant_id-resolved_at")
+                    .table(LowStockAlert::Table)
+                    .col(LowStockAlert::TenantId)
+                    .col(LowStockAlert::ResolvedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // A product has at most one open alert, even when two evaluations race.
+        // MySQL has no partial indexes, so it relies on the evaluation's own check
+        if manager.get_database_backend() == DbBackend::MySql {
+            return Ok(());
+        }
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "CREATE UNIQUE INDEX \"idx-low_stock_alert-product_id-open\" ON low_stock_alert (product_id) WHERE resolved_at IS NULL",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LowStockAlert::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum LowStockAlert{
+    Table,
+    Id,
+    TenantId,
+    ProductId,
+    Quantity,
+    Capacity,
+    Threshold,
+    RaisedAt,
+    NotifiedAt,
+    ResolvedAt,
+}
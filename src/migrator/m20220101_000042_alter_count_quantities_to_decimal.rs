@@ -0,0 +1,71 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000042_alter_count_quantities_to_decimal"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Counts hold the same decimal quantities as inventory, so fractional stock can be counted.
+        // SQLite can't change a column's type, but its integer columns already keep fractional values
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CountLine::Table)
+                    .modify_column(ColumnDef::new(CountLine::ExpectedQuantity).decimal_len(16, 4).not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CountEntry::Table)
+                    .modify_column(ColumnDef::new(CountEntry::CountedQuantity).decimal_len(16, 4).not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CountEntry::Table)
+                    .modify_column(ColumnDef::new(CountEntry::CountedQuantity).integer().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CountLine::Table)
+                    .modify_column(ColumnDef::new(CountLine::ExpectedQuantity).integer().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum CountLine{
+    Table,
+    ExpectedQuantity,
+}
+
+#[derive(Iden)]
+pub enum CountEntry{
+    Table,
+    CountedQuantity,
+}
@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220101_000043_add_price_override_to_product"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Product::Table)
+                    .add_column(ColumnDef::new(Product::PriceOverride).boolean().not_null().default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Variants already priced away from their parent were overridden when they were created.
+        // MySQL can't read the table it updates in a subquery, so it joins instead
+        let db = manager.get_connection();
+        match manager.get_database_backend() {
+            DbBackend::MySql => {
+                db.execute_unprepared("UPDATE product AS variant JOIN product AS parent ON parent.id = variant.parent_id \
+                    SET variant.price_override = TRUE WHERE variant.price <> parent.price").await?;
+            }
+            DbBackend::Postgres | DbBackend::Sqlite => {
+                db.execute_unprepared("UPDATE product SET price_override = TRUE WHERE parent_id IS NOT NULL \
+                    AND price <> (SELECT parent.price FROM product AS parent WHERE parent.id = product.parent_id)").await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Product::Table)
+                    .drop_column(Product::PriceOverride)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Product{
+    Table,
+    PriceOverride,
+}
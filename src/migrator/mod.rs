@@ -2,6 +2,48 @@ pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_product_table;
 mod m20220101_000002_create_inventory_table;
+mod m20220101_000003_create_sale_table;
+mod m20220101_000004_create_rma_table;
+mod m20220101_000005_create_rma_item_table;
+mod m20220101_000006_create_write_off_table;
+mod m20220101_000007_create_stock_adjustment_table;
+mod m20220101_000008_create_count_session_table;
+mod m20220101_000009_create_count_line_table;
+mod m20220101_000010_create_count_entry_table;
+mod m20220101_000011_create_lot_table;
+mod m20220101_000012_add_serialized_to_product;
+mod m20220101_000013_create_serial_unit_table;
+mod m20220101_000014_create_serial_event_table;
+mod m20220101_000015_create_bom_component_table;
+mod m20220101_000016_add_base_unit_to_product;
+mod m20220101_000017_create_unit_conversion_table;
+mod m20220101_000018_add_quantity_precision_to_product;
+mod m20220101_000019_alter_inventory_quantity_to_decimal;
+mod m20220101_000020_alter_stock_adjustment_quantity_to_decimal;
+mod m20220101_000021_create_category_table;
+mod m20220101_000022_add_catalogue_fields_to_product;
+mod m20220101_000023_backfill_product_sku;
+mod m20220101_000024_add_variant_fields_to_product;
+mod m20220101_000025_create_attribute_definition_table;
+mod m20220101_000026_create_product_attribute_table;
+mod m20220101_000027_add_product_search_indexes;
+mod m20220101_000028_add_archived_at_to_product;
+mod m20220101_000029_create_audit_log_table;
+mod m20220101_000030_create_app_user_table;
+mod m20220101_000031_create_api_key_table;
+mod m20220101_000032_create_tenant_table;
+mod m20220101_000033_add_tenant_id_to_tables;
+mod m20220101_000034_add_integrity_constraints;
+mod m20220101_000035_drop_denormalised_inventory_columns;
+mod m20220101_000036_create_idempotency_key_table;
+mod m20220101_000037_create_outbox_event_table;
+mod m20220101_000038_create_webhook_subscription_table;
+mod m20220101_000039_create_webhook_delivery_table;
+mod m20220101_000040_create_webhook_attempt_table;
+mod m20220101_000041_create_low_stock_alert_table;
+mod m20220101_000042_alter_count_quantities_to_decimal;
+mod m20220101_000043_add_price_override_to_product;
+mod m20220101_000044_add_claimed_until_to_low_stock_alert;
 
 pub struct Migrator;
 
@@ -11,6 +53,48 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20220101_000001_create_product_table::Migration),
             Box::new(m20220101_000002_create_inventory_table::Migration),
+            Box::new(m20220101_000003_create_sale_table::Migration),
+            Box::new(m20220101_000004_create_rma_table::Migration),
+            Box::new(m20220101_000005_create_rma_item_table::Migration),
+            Box::new(m20220101_000006_create_write_off_table::Migration),
+            Box::new(m20220101_000007_create_stock_adjustment_table::Migration),
+            Box::new(m20220101_000008_create_count_session_table::Migration),
+            Box::new(m20220101_000009_create_count_line_table::Migration),
+            Box::new(m20220101_000010_create_count_entry_table::Migration),
+            Box::new(m20220101_000011_create_lot_table::Migration),
+            Box::new(m20220101_000012_add_serialized_to_product::Migration),
+            Box::new(m20220101_000013_create_serial_unit_table::Migration),
+            Box::new(m20220101_000014_create_serial_event_table::Migration),
+            Box::new(m20220101_000015_create_bom_component_table::Migration),
+            Box::new(m20220101_000016_add_base_unit_to_product::Migration),
+            Box::new(m20220101_000017_create_unit_conversion_table::Migration),
+            Box::new(m20220101_000018_add_quantity_precision_to_product::Migration),
+            Box::new(m20220101_000019_alter_inventory_quantity_to_decimal::Migration),
+            Box::new(m20220101_000020_alter_stock_adjustment_quantity_to_decimal::Migration),
+            Box::new(m20220101_000021_create_category_table::Migration),
+            Box::new(m20220101_000022_add_catalogue_fields_to_product::Migration),
+            Box::new(m20220101_000023_backfill_product_sku::Migration),
+            Box::new(m20220101_000024_add_variant_fields_to_product::Migration),
+            Box::new(m20220101_000025_create_attribute_definition_table::Migration),
+            Box::new(m20220101_000026_create_product_attribute_table::Migration),
+            Box::new(m20220101_000027_add_product_search_indexes::Migration),
+            Box::new(m20220101_000028_add_archived_at_to_product::Migration),
+            Box::new(m20220101_000029_create_audit_log_table::Migration),
+            Box::new(m20220101_000030_create_app_user_table::Migration),
+            Box::new(m20220101_000031_create_api_key_table::Migration),
+            Box::new(m20220101_000032_create_tenant_table::Migration),
+            Box::new(m20220101_000033_add_tenant_id_to_tables::Migration),
+            Box::new(m20220101_000034_add_integrity_constraints::Migration),
+            Box::new(m20220101_000035_drop_denormalised_inventory_columns::Migration),
+            Box::new(m20220101_000036_create_idempotency_key_table::Migration),
+            Box::new(m20220101_000037_create_outbox_event_table::Migration),
+            Box::new(m20220101_000038_create_webhook_subscription_table::Migration),
+            Box::new(m20220101_000039_create_webhook_delivery_table::Migration),
+            Box::new(m20220101_000040_create_webhook_attempt_table::Migration),
+            Box::new(m20220101_000041_create_low_stock_alert_table::Migration),
+            Box::new(m20220101_000042_alter_count_quantities_to_decimal::Migration),
+            Box::new(m20220101_000043_add_price_override_to_product::Migration),
+            Box::new(m20220101_000044_add_claimed_until_to_low_stock_alert::Migration),
         ]
     }
 }